@@ -0,0 +1,52 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+
+use crate::{
+    auth::User, error::ApiError, fetch_weather, get_cached_forecast, get_lat_long,
+    get_last_cities, AppState, City, WeatherDisplay, WeatherQuery,
+};
+
+/// JSON counterpart of `/weather`, documented for the OpenAPI spec.
+#[utoipa::path(
+    get,
+    path = "/api/weather",
+    params(WeatherQuery),
+    responses(
+        (status = 200, description = "Hourly forecast for a city", body = WeatherDisplay)
+    )
+)]
+pub async fn weather(
+    Query(params): Query<WeatherQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<WeatherDisplay>, ApiError> {
+    if let Some(display) = get_cached_forecast(&state.pool, &params.city, params.units).await? {
+        return Ok(Json(display));
+    }
+
+    let lat_long = get_lat_long(&state.pool, &params.city).await?;
+    let weather = fetch_weather(lat_long, params.units).await?;
+    Ok(Json(WeatherDisplay::new(
+        params.city.as_str(),
+        params.units,
+        weather,
+    )))
+}
+
+/// JSON counterpart of `/stats`, documented for the OpenAPI spec.
+#[utoipa::path(
+    get,
+    path = "/api/stats",
+    responses(
+        (status = 200, description = "The most recently looked-up cities", body = [City])
+    )
+)]
+pub async fn stats(
+    user: User,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<City>>, ApiError> {
+    eprintln!("stats requested by {}", user.id.0);
+    let cities = get_last_cities(&state.pool).await?;
+    Ok(Json(cities))
+}
@@ -0,0 +1,141 @@
+use axum::{async_trait, http::HeaderMap};
+use axum_extra::extract::cookie::CookieJar;
+use jsonwebtoken::{decode, DecodingKey, Validation};
+
+use crate::{auth::AuthError, config::Config};
+
+pub struct AuthId(pub String);
+
+/// An authentication backend that can verify a request and name whoever made it.
+#[async_trait]
+pub trait ApiAuth: Send + Sync {
+    async fn check_auth(&self, headers: &HeaderMap) -> Result<AuthId, AuthError>;
+
+    /// The `WWW-Authenticate` challenge to attach on a 401, if this backend uses one.
+    fn challenge(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// The original shared-credential check, kept around as a simple backend.
+pub struct BasicAuth;
+
+#[async_trait]
+impl ApiAuth for BasicAuth {
+    async fn check_auth(&self, headers: &HeaderMap) -> Result<AuthId, AuthError> {
+        let auth_header = headers
+            .get(axum::http::header::AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .ok_or(AuthError::Unauthorized)?;
+
+        let credentials = auth_header
+            .strip_prefix("Basic ")
+            .ok_or(AuthError::Unauthorized)?;
+        let decoded = base64::decode(credentials).map_err(|_| AuthError::Unauthorized)?;
+        let credential_str = std::str::from_utf8(&decoded).map_err(|_| AuthError::Unauthorized)?;
+
+        if credential_str == "forecast:forecast" {
+            Ok(AuthId("forecast".to_string()))
+        } else {
+            Err(AuthError::Unauthorized)
+        }
+    }
+
+    fn challenge(&self) -> Option<&'static str> {
+        Some("Basic realm=\"Please enter your credentials\"")
+    }
+}
+
+/// Verifies the JWTs issued by `/login`, from either a bearer header or a cookie.
+pub struct JwtAuth {
+    pub config: Config,
+}
+
+#[async_trait]
+impl ApiAuth for JwtAuth {
+    async fn check_auth(&self, headers: &HeaderMap) -> Result<AuthId, AuthError> {
+        let token = headers
+            .get(axum::http::header::AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .map(str::to_owned)
+            .or_else(|| {
+                CookieJar::from_headers(headers)
+                    .get("token")
+                    .map(|cookie| cookie.value().to_owned())
+            })
+            .ok_or(AuthError::Unauthorized)?;
+
+        let claims = decode::<crate::auth::Claims>(
+            &token,
+            &DecodingKey::from_secret(self.config.jwt_secret.as_bytes()),
+            &Validation::default(),
+        )
+        .map_err(|_| AuthError::Unauthorized)?
+        .claims;
+
+        Ok(AuthId(claims.sub))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    use super::*;
+    use crate::auth::Claims;
+
+    fn config() -> Config {
+        Config {
+            jwt_secret: "test-secret".to_string(),
+            jwt_maxage: 60,
+        }
+    }
+
+    fn token_with_exp(config: &Config, exp: usize) -> String {
+        let claims = Claims {
+            sub: "someone".to_string(),
+            iat: 0,
+            exp,
+        };
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn rejects_expired_token() {
+        let config = config();
+        let token = token_with_exp(&config, 1);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            axum::http::header::AUTHORIZATION,
+            format!("Bearer {token}").parse().unwrap(),
+        );
+
+        let auth = JwtAuth { config };
+        assert!(auth.check_auth(&headers).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn accepts_unexpired_token() {
+        let config = config();
+        let exp = (time::OffsetDateTime::now_utc() + time::Duration::minutes(5)).unix_timestamp()
+            as usize;
+        let token = token_with_exp(&config, exp);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            axum::http::header::AUTHORIZATION,
+            format!("Bearer {token}").parse().unwrap(),
+        );
+
+        let auth = JwtAuth { config };
+        let id = auth.check_auth(&headers).await.unwrap();
+        assert_eq!(id.0, "someone");
+    }
+}
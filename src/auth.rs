@@ -0,0 +1,228 @@
+use std::sync::OnceLock;
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, State},
+    http::{header, request::Parts, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::AppState;
+
+#[derive(Debug, sqlx::FromRow)]
+struct UserRecord {
+    id: Uuid,
+    password_hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Claims {
+    pub(crate) sub: String,
+    pub(crate) iat: usize,
+    pub(crate) exp: usize,
+}
+
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+pub struct RegisterPayload {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+pub struct LoginPayload {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub struct LoginResponse {
+    pub token: String,
+}
+
+pub enum AuthError {
+    DuplicateUsername,
+    InvalidCredentials,
+    Unauthorized,
+    Internal(anyhow::Error),
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            AuthError::DuplicateUsername => {
+                (StatusCode::CONFLICT, "username is already taken".to_string())
+            }
+            AuthError::InvalidCredentials => (
+                StatusCode::UNAUTHORIZED,
+                "invalid username or password".to_string(),
+            ),
+            AuthError::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized".to_string()),
+            AuthError::Internal(err) => {
+                eprintln!("auth error: {err:?}");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "something went wrong".to_string(),
+                )
+            }
+        };
+
+        (status, message).into_response()
+    }
+}
+
+impl<E> From<E> for AuthError
+where
+    E: Into<anyhow::Error>,
+{
+    fn from(err: E) -> Self {
+        Self::Internal(err.into())
+    }
+}
+
+pub struct User {
+    pub id: crate::api_auth::AuthId,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for User {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        match state.auth.check_auth(&parts.headers).await {
+            Ok(id) => Ok(User { id }),
+            Err(err) => {
+                let mut response = err.into_response();
+                if let Some(challenge) = state.auth.challenge() {
+                    response
+                        .headers_mut()
+                        .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static(challenge));
+                }
+                Err(response)
+            }
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/register",
+    request_body = RegisterPayload,
+    responses(
+        (status = 201, description = "Account created"),
+        (status = 409, description = "Username is already taken")
+    )
+)]
+pub async fn register(
+    State(state): State<AppState>,
+    Json(payload): Json<RegisterPayload>,
+) -> Result<StatusCode, AuthError> {
+    let salt = SaltString::generate(&mut OsRng);
+    let password_hash = Argon2::default()
+        .hash_password(payload.password.as_bytes(), &salt)
+        .map_err(|err| AuthError::Internal(anyhow::anyhow!(err)))?
+        .to_string();
+
+    let result = sqlx::query("INSERT INTO users (username, password_hash) VALUES ($1, $2)")
+        .bind(&payload.username)
+        .bind(password_hash)
+        .execute(&state.pool)
+        .await;
+
+    match result {
+        Ok(_) => Ok(StatusCode::CREATED),
+        Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
+            Err(AuthError::DuplicateUsername)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/login",
+    request_body = LoginPayload,
+    responses(
+        (status = 200, description = "Signed in", body = LoginResponse),
+        (status = 401, description = "Invalid username or password")
+    )
+)]
+pub async fn login(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(payload): Json<LoginPayload>,
+) -> Result<(CookieJar, Json<LoginResponse>), AuthError> {
+    let user = sqlx::query_as::<_, UserRecord>(
+        "SELECT id, password_hash FROM users WHERE username = $1",
+    )
+    .bind(&payload.username)
+    .fetch_optional(&state.pool)
+    .await?;
+
+    // Verify against a real or dummy hash regardless of whether the username
+    // exists, so a wrong password takes the same time either way and response
+    // latency can't be used to enumerate registered usernames.
+    let password_hash = user
+        .as_ref()
+        .map(|user| user.password_hash.as_str())
+        .unwrap_or_else(dummy_password_hash);
+    let parsed_hash = PasswordHash::new(password_hash)
+        .map_err(|err| AuthError::Internal(anyhow::anyhow!(err)))?;
+    let verified = Argon2::default()
+        .verify_password(payload.password.as_bytes(), &parsed_hash)
+        .is_ok();
+
+    let user = user.filter(|_| verified).ok_or(AuthError::InvalidCredentials)?;
+
+    let token = issue_token(&user.id, &state.config)?;
+    let cookie = Cookie::build(("token", token.clone()))
+        .path("/")
+        .http_only(true)
+        .build();
+
+    Ok((jar.add(cookie), Json(LoginResponse { token })))
+}
+
+/// A hash of a password nobody will ever type, used to pad the "no such user"
+/// path in `login` to the same Argon2 cost as a real verification attempt.
+fn dummy_password_hash() -> &'static str {
+    static DUMMY_PASSWORD_HASH: OnceLock<String> = OnceLock::new();
+    DUMMY_PASSWORD_HASH.get_or_init(|| {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(b"forecast-dummy-password", &salt)
+            .expect("hashing a fixed password can't fail")
+            .to_string()
+    })
+}
+
+fn issue_token(user_id: &Uuid, config: &crate::config::Config) -> Result<String, AuthError> {
+    let now = OffsetDateTime::now_utc();
+    let iat = now.unix_timestamp() as usize;
+    let exp = (now + Duration::minutes(config.jwt_maxage)).unix_timestamp() as usize;
+
+    let claims = Claims {
+        sub: user_id.to_string(),
+        iat,
+        exp,
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+    )
+    .map_err(|err| AuthError::Internal(anyhow::anyhow!(err)))
+}
@@ -0,0 +1,22 @@
+use anyhow::Context;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub jwt_secret: String,
+    pub jwt_maxage: i64,
+}
+
+impl Config {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let jwt_secret = std::env::var("JWT_SECRET").context("JWT_SECRET must be set")?;
+        let jwt_maxage = std::env::var("JWT_MAXAGE")
+            .context("JWT_MAXAGE must be set")?
+            .parse::<i64>()
+            .context("JWT_MAXAGE must be a number of minutes")?;
+
+        Ok(Config {
+            jwt_secret,
+            jwt_maxage,
+        })
+    }
+}
@@ -0,0 +1,111 @@
+use axum::{
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+/// Marks the "no results found" case from `fetch_lat_long` so it can be told apart
+/// from a genuine upstream failure once it's wrapped in an `anyhow::Error`.
+#[derive(Debug)]
+pub struct CityNotFound;
+
+impl std::fmt::Display for CityNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no results found for that city")
+    }
+}
+
+impl std::error::Error for CityNotFound {}
+
+enum AppErrorKind {
+    NotFound,
+    Upstream(anyhow::Error),
+    Internal(anyhow::Error),
+}
+
+impl AppErrorKind {
+    fn status(&self) -> StatusCode {
+        match self {
+            AppErrorKind::NotFound => StatusCode::NOT_FOUND,
+            AppErrorKind::Upstream(_) => StatusCode::BAD_GATEWAY,
+            AppErrorKind::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            AppErrorKind::NotFound => "we couldn't find that city".to_string(),
+            AppErrorKind::Upstream(err) => {
+                eprintln!("upstream error: {err:?}");
+                "the weather service is unavailable right now".to_string()
+            }
+            AppErrorKind::Internal(err) => {
+                eprintln!("internal error: {err:?}");
+                "something went wrong".to_string()
+            }
+        }
+    }
+}
+
+impl<E> From<E> for AppErrorKind
+where
+    E: Into<anyhow::Error>,
+{
+    fn from(err: E) -> Self {
+        let err = err.into();
+        if err.is::<CityNotFound>() {
+            return AppErrorKind::NotFound;
+        }
+        if err.is::<reqwest::Error>() {
+            return AppErrorKind::Upstream(err);
+        }
+        AppErrorKind::Internal(err)
+    }
+}
+
+/// Error type for browser-facing routes: renders a small HTML page.
+pub struct AppError(AppErrorKind);
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.0.status();
+        let message = self.0.message();
+        (
+            status,
+            Html(format!(
+                "<html><body><h1>{status}</h1><p>{message}</p></body></html>"
+            )),
+        )
+            .into_response()
+    }
+}
+
+impl<E> From<E> for AppError
+where
+    E: Into<anyhow::Error>,
+{
+    fn from(err: E) -> Self {
+        Self(AppErrorKind::from(err))
+    }
+}
+
+/// Error type for `/api/*` routes: renders a structured JSON body.
+pub struct ApiError(AppErrorKind);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.0.status();
+        let message = self.0.message();
+        (status, Json(json!({ "error": message }))).into_response()
+    }
+}
+
+impl<E> From<E> for ApiError
+where
+    E: Into<anyhow::Error>,
+{
+    fn from(err: E) -> Self {
+        Self(AppErrorKind::from(err))
+    }
+}
@@ -1,81 +1,42 @@
-use std::{net::SocketAddr, str::from_utf8};
+mod api;
+mod api_auth;
+mod auth;
+mod config;
+mod error;
+mod openapi;
+mod sse;
+mod units;
+mod worker;
+
+use std::{net::SocketAddr, sync::Arc};
 
 use anyhow::Context;
 use askama::Template;
 use axum::{
-    async_trait,
-    extract::{FromRequestParts, Query, State},
-    response::IntoResponse,
-    routing::get,
+    extract::{Query, State},
+    routing::{get, post},
     Router,
 };
 use axum_macros::debug_handler;
-use reqwest::StatusCode;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use tower_http::compression::CompressionLayer;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::{
+    api_auth::{ApiAuth, BasicAuth, JwtAuth},
+    auth::User,
+    config::Config,
+    error::{AppError, CityNotFound},
+    units::Unit,
+};
 
-struct AppError(anyhow::Error);
-
-impl IntoResponse for AppError {
-    fn into_response(self) -> axum::response::Response {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Something went wrong: {}", self.0),
-        )
-            .into_response()
-    }
-}
-
-impl<E> From<E> for AppError
-where
-    E: Into<anyhow::Error>,
-{
-    fn from(err: E) -> Self {
-        Self(err.into())
-    }
-}
-
-struct User;
-
-#[async_trait]
-impl<S> FromRequestParts<S> for User
-where
-    S: Send + Sync,
-{
-    type Rejection = axum::http::Response<axum::body::Body>;
-
-    async fn from_request_parts(
-        parts: &mut axum::http::request::Parts,
-        _state: &S,
-    ) -> Result<Self, Self::Rejection> {
-        let auth_header = parts
-            .headers
-            .get("Authorization")
-            .and_then(|header| header.to_str().ok());
-
-        if let Some(auth_header) = auth_header {
-            if auth_header.starts_with("Basic ") {
-                let credentials = auth_header.trim_start_matches("Basic ");
-                let decoded = base64::decode(credentials).unwrap_or_default();
-                let credential_str = from_utf8(&decoded).unwrap_or("");
-
-                if credential_str == "forecast:forecast" {
-                    return Ok(User);
-                }
-            }
-        }
-
-        let reject_response = axum::http::Response::builder()
-            .status(StatusCode::UNAUTHORIZED)
-            .header(
-                "WWW-Authenticate",
-                "Basic realm=\"Please enter your credentials\"",
-            )
-            .body(axum::body::Body::from("Unauthorized"))
-            .unwrap();
-
-        Err(reject_response)
-    }
+#[derive(Clone)]
+struct AppState {
+    pool: PgPool,
+    config: Config,
+    auth: Arc<dyn ApiAuth>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -89,9 +50,11 @@ pub struct LatLong {
     pub longitude: f64,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, utoipa::IntoParams)]
 pub struct WeatherQuery {
     pub city: String,
+    #[serde(default)]
+    pub units: Unit,
 }
 
 #[derive(Debug, Deserialize)]
@@ -106,41 +69,82 @@ pub struct WeatherResponse {
 pub struct Hourly {
     pub time: Vec<String>,
     pub temperature_2m: Vec<f64>,
+    pub relative_humidity_2m: Vec<f64>,
+    pub apparent_temperature: Vec<f64>,
+    pub precipitation: Vec<f64>,
+    pub wind_speed_10m: Vec<f64>,
+    pub wind_direction_10m: Vec<f64>,
+    pub pressure_msl: Vec<f64>,
 }
 
-#[derive(Debug, Deserialize, Template)]
+#[derive(Debug, Deserialize, Serialize, Template, utoipa::ToSchema)]
 #[template(path = "weather.html")]
 pub struct WeatherDisplay {
     pub city: String,
     pub forecasts: Vec<Forecast>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, utoipa::ToSchema)]
 pub struct Forecast {
     pub date: String,
     pub temperature: String,
+    pub apparent_temperature: String,
+    pub humidity: f64,
+    pub precipitation: f64,
+    pub wind_speed: String,
+    pub wind_direction: f64,
+    pub pressure: f64,
 }
 
 impl WeatherDisplay {
-    fn new(city: &str, response: WeatherResponse) -> Self {
-        let display = WeatherDisplay {
+    pub(crate) fn new(city: &str, units: Unit, response: WeatherResponse) -> Self {
+        let hourly = &response.hourly;
+        let len = hourly_len(hourly);
+
+        let forecasts = (0..len)
+            .map(|i| Forecast {
+                date: hourly.time[i].clone(),
+                temperature: format!("{}{}", hourly.temperature_2m[i], units.temperature_label()),
+                apparent_temperature: format!(
+                    "{}{}",
+                    hourly.apparent_temperature[i],
+                    units.temperature_label()
+                ),
+                humidity: hourly.relative_humidity_2m[i],
+                precipitation: hourly.precipitation[i],
+                wind_speed: format!("{}{}", hourly.wind_speed_10m[i], units.wind_speed_label()),
+                wind_direction: hourly.wind_direction_10m[i],
+                pressure: hourly.pressure_msl[i],
+            })
+            .collect();
+
+        WeatherDisplay {
             city: city.to_owned(),
-            forecasts: response
-                .hourly
-                .time
-                .iter()
-                .zip(response.hourly.temperature_2m.iter())
-                .map(|(date, temperature)| Forecast {
-                    date: date.to_string(),
-                    temperature: temperature.to_string(),
-                })
-                .collect(),
-        };
-        display
+            forecasts,
+        }
     }
 }
 
-async fn get_lat_long(pool: &PgPool, name: &str) -> Result<LatLong, anyhow::Error> {
+/// The number of hourly entries it's safe to index into `hourly`'s parallel
+/// vectors. Open-Meteo is expected to return same-length vectors, but a short
+/// or inconsistent one is a plausible upstream hiccup, not a reason to panic.
+pub(crate) fn hourly_len(hourly: &Hourly) -> usize {
+    [
+        hourly.time.len(),
+        hourly.temperature_2m.len(),
+        hourly.relative_humidity_2m.len(),
+        hourly.apparent_temperature.len(),
+        hourly.precipitation.len(),
+        hourly.wind_speed_10m.len(),
+        hourly.wind_direction_10m.len(),
+        hourly.pressure_msl.len(),
+    ]
+    .into_iter()
+    .min()
+    .unwrap_or(0)
+}
+
+pub(crate) async fn get_lat_long(pool: &PgPool, name: &str) -> Result<LatLong, anyhow::Error> {
     let lat_long = sqlx::query_as::<_, LatLong>(
         "SELECT lat AS latitude, long AS longitude FROM cities WHERE name = $1",
     )
@@ -163,13 +167,17 @@ async fn get_lat_long(pool: &PgPool, name: &str) -> Result<LatLong, anyhow::Erro
     Ok(lat_long)
 }
 
-async fn fetch_lat_long(city: &str) -> Result<LatLong, anyhow::Error> {
+pub(crate) async fn fetch_lat_long(city: &str) -> Result<LatLong, anyhow::Error> {
     let endpoint = format!(
         "https://geocoding-api.open-meteo.com/v1/search?name={}&count=1&language=en&format=json",
         city
     );
     let response = reqwest::get(&endpoint).await?.json::<GeoResponse>().await?;
-    response.results.get(0).cloned().context("No results found")
+    response
+        .results
+        .get(0)
+        .cloned()
+        .ok_or_else(|| anyhow::Error::new(CityNotFound))
 }
 
 #[derive(Debug, Template)]
@@ -183,17 +191,95 @@ async fn index() -> IndexTemplate {
 
 async fn weather(
     Query(params): Query<WeatherQuery>,
-    State(pool): State<PgPool>,
+    State(state): State<AppState>,
 ) -> Result<WeatherDisplay, AppError> {
-    let lat_long = fetch_lat_long(&params.city).await?;
-    let weather = fetch_weather(lat_long).await?;
-    Ok(WeatherDisplay::new(params.city.as_str(), weather))
+    if let Some(display) = get_cached_forecast(&state.pool, &params.city, params.units).await? {
+        return Ok(display);
+    }
+
+    let lat_long = get_lat_long(&state.pool, &params.city).await?;
+    let weather = fetch_weather(lat_long, params.units).await?;
+    Ok(WeatherDisplay::new(params.city.as_str(), params.units, weather))
+}
+
+#[derive(sqlx::FromRow)]
+struct CachedForecastRow {
+    time: String,
+    temperature: f64,
+    apparent_temperature: f64,
+    humidity: f64,
+    precipitation: f64,
+    wind_speed: f64,
+    wind_direction: f64,
+    pressure: f64,
+}
+
+/// Reads a city's forecast from the `forecasts` table populated by the background
+/// worker, returning `None` when there's nothing fresh enough to serve.
+///
+/// The worker only ever stores metric readings, so a cache hit only applies when
+/// the caller wants metric units; anything else falls back to a live fetch.
+pub(crate) async fn get_cached_forecast(
+    pool: &PgPool,
+    city: &str,
+    units: Unit,
+) -> Result<Option<WeatherDisplay>, anyhow::Error> {
+    if units != Unit::Metric {
+        return Ok(None);
+    }
+
+    let rows = sqlx::query_as::<_, CachedForecastRow>(
+        "SELECT time, temperature, apparent_temperature, humidity, precipitation, wind_speed, wind_direction, pressure
+         FROM forecasts
+         WHERE city = $1 AND fetched_at > now() - $2::interval
+         ORDER BY time",
+    )
+    .bind(city)
+    .bind(worker::CACHE_FRESHNESS)
+    .fetch_all(pool)
+    .await?;
+
+    if rows.is_empty() {
+        return Ok(None);
+    }
+
+    let forecasts = rows
+        .into_iter()
+        .map(|row| Forecast {
+            date: row.time,
+            temperature: format!("{}{}", row.temperature, units.temperature_label()),
+            apparent_temperature: format!(
+                "{}{}",
+                row.apparent_temperature,
+                units.temperature_label()
+            ),
+            humidity: row.humidity,
+            precipitation: row.precipitation,
+            wind_speed: format!("{}{}", row.wind_speed, units.wind_speed_label()),
+            wind_direction: row.wind_direction,
+            pressure: row.pressure,
+        })
+        .collect();
+
+    Ok(Some(WeatherDisplay {
+        city: city.to_owned(),
+        forecasts,
+    }))
 }
 
-async fn fetch_weather(lat_long: LatLong) -> Result<WeatherResponse, anyhow::Error> {
+const HOURLY_VARIABLES: &str = "temperature_2m,relative_humidity_2m,apparent_temperature,precipitation,wind_speed_10m,wind_direction_10m,pressure_msl";
+
+pub(crate) async fn fetch_weather(
+    lat_long: LatLong,
+    units: Unit,
+) -> Result<WeatherResponse, anyhow::Error> {
     let endpoint = format!(
-        "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&hourly=temperature_2m",
-        lat_long.latitude, lat_long.longitude
+        "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&hourly={}&temperature_unit={}&wind_speed_unit={}",
+        lat_long.latitude,
+        lat_long.longitude,
+        HOURLY_VARIABLES,
+        units.temperature_param(),
+        units.wind_speed_param(),
     );
     let response = reqwest::get(&endpoint)
         .await?
@@ -208,12 +294,12 @@ struct StatsTemplate {
     pub cities: Vec<City>,
 }
 
-#[derive(Debug, Clone, sqlx::FromRow)]
-struct City {
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, utoipa::ToSchema)]
+pub(crate) struct City {
     pub name: String,
 }
 
-async fn get_last_cities(pool: &PgPool) -> Result<Vec<City>, AppError> {
+pub(crate) async fn get_last_cities(pool: &PgPool) -> Result<Vec<City>, anyhow::Error> {
     let cities = sqlx::query_as::<_, City>("SELECT name FROM cities ORDER BY id DESC LIMIT 10")
         .fetch_all(pool)
         .await?;
@@ -221,8 +307,9 @@ async fn get_last_cities(pool: &PgPool) -> Result<Vec<City>, AppError> {
 }
 
 #[debug_handler]
-async fn stats(user: User, State(pool): State<PgPool>) -> Result<StatsTemplate, AppError> {
-    let cities = get_last_cities(&pool).await?;
+async fn stats(user: User, State(state): State<AppState>) -> Result<StatsTemplate, AppError> {
+    eprintln!("stats requested by {}", user.id.0);
+    let cities = get_last_cities(&state.pool).await?;
     Ok(StatsTemplate { cities })
 }
 
@@ -233,12 +320,36 @@ async fn main() -> anyhow::Result<()> {
     let pool = sqlx::PgPool::connect(&db_connection_str)
         .await
         .context("can't connect to database")?;
+    let config = Config::from_env()?;
+
+    let auth: Arc<dyn ApiAuth> = match std::env::var("AUTH_BACKEND").as_deref() {
+        Ok("basic") => Arc::new(BasicAuth),
+        _ => Arc::new(JwtAuth {
+            config: config.clone(),
+        }),
+    };
+
+    tokio::spawn(worker::run(pool.clone()));
+
+    let state = AppState { pool, config, auth };
 
     let app = Router::new()
         .route("/", get(index))
         .route("/weather", get(weather))
         .route("/stats", get(stats))
-        .with_state(pool);
+        .route("/register", post(auth::register))
+        .route("/login", post(auth::login))
+        .route("/api/weather", get(api::weather))
+        .route("/api/stats", get(api::stats))
+        .merge(
+            SwaggerUi::new("/swagger-ui")
+                .url("/api-docs/openapi.json", openapi::ApiDoc::openapi()),
+        )
+        .layer(CompressionLayer::new())
+        // The SSE route streams events as they happen; gzip would buffer them
+        // and undermine "push only on change", so it's added uncompressed.
+        .route("/weather/subscribe", get(sse::subscribe))
+        .with_state(state);
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
@@ -248,3 +359,36 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hourly_with_lengths(lengths: [usize; 8]) -> Hourly {
+        Hourly {
+            time: vec!["2024-01-01T00:00".to_string(); lengths[0]],
+            temperature_2m: vec![0.0; lengths[1]],
+            relative_humidity_2m: vec![0.0; lengths[2]],
+            apparent_temperature: vec![0.0; lengths[3]],
+            precipitation: vec![0.0; lengths[4]],
+            wind_speed_10m: vec![0.0; lengths[5]],
+            wind_direction_10m: vec![0.0; lengths[6]],
+            pressure_msl: vec![0.0; lengths[7]],
+        }
+    }
+
+    #[test]
+    fn hourly_len_is_the_shortest_vector() {
+        let hourly = hourly_with_lengths([24, 24, 24, 24, 24, 24, 24, 24]);
+        assert_eq!(hourly_len(&hourly), 24);
+
+        let hourly = hourly_with_lengths([24, 24, 10, 24, 24, 24, 24, 24]);
+        assert_eq!(hourly_len(&hourly), 10);
+    }
+
+    #[test]
+    fn hourly_len_is_zero_when_any_vector_is_empty() {
+        let hourly = hourly_with_lengths([24, 24, 24, 0, 24, 24, 24, 24]);
+        assert_eq!(hourly_len(&hourly), 0);
+    }
+}
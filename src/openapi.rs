@@ -0,0 +1,18 @@
+use utoipa::OpenApi;
+
+use crate::{api, auth, units::Unit, City, Forecast, WeatherDisplay};
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(api::weather, api::stats, auth::register, auth::login),
+    components(schemas(
+        WeatherDisplay,
+        Forecast,
+        City,
+        Unit,
+        auth::RegisterPayload,
+        auth::LoginPayload,
+        auth::LoginResponse,
+    ))
+)]
+pub struct ApiDoc;
@@ -0,0 +1,66 @@
+use std::{convert::Infallible, time::Duration};
+
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::Stream;
+use serde::Serialize;
+
+use crate::{fetch_weather, get_lat_long, AppState, WeatherQuery};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Serialize)]
+struct TemperatureUpdate {
+    city: String,
+    time: String,
+    temperature: f64,
+}
+
+pub async fn subscribe(
+    Query(params): Query<WeatherQuery>,
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let city = params.city;
+    let units = params.units;
+
+    let stream = async_stream::stream! {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        let mut last_temperature: Option<f64> = None;
+
+        loop {
+            interval.tick().await;
+
+            let Ok(lat_long) = get_lat_long(&state.pool, &city).await else {
+                continue;
+            };
+            let Ok(weather) = fetch_weather(lat_long, units).await else {
+                continue;
+            };
+            let (Some(time), Some(temperature)) = (
+                weather.hourly.time.first().cloned(),
+                weather.hourly.temperature_2m.first().copied(),
+            ) else {
+                continue;
+            };
+
+            if last_temperature == Some(temperature) {
+                continue;
+            }
+            last_temperature = Some(temperature);
+
+            let update = TemperatureUpdate {
+                city: city.clone(),
+                time,
+                temperature,
+            };
+
+            if let Ok(event) = Event::default().json_data(&update) {
+                yield Ok(event);
+            }
+        }
+    };
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,79 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, utoipa::ToSchema)]
+pub enum Unit {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+impl FromStr for Unit {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "imperial" => Ok(Unit::Imperial),
+            _ => Ok(Unit::Metric),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Unit {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = String::deserialize(deserializer)?;
+        Ok(raw.parse().unwrap_or_default())
+    }
+}
+
+impl Unit {
+    pub fn temperature_param(&self) -> &'static str {
+        match self {
+            Unit::Metric => "celsius",
+            Unit::Imperial => "fahrenheit",
+        }
+    }
+
+    pub fn wind_speed_param(&self) -> &'static str {
+        match self {
+            Unit::Metric => "kmh",
+            Unit::Imperial => "mph",
+        }
+    }
+
+    pub fn temperature_label(&self) -> &'static str {
+        match self {
+            Unit::Metric => "\u{b0}C",
+            Unit::Imperial => "\u{b0}F",
+        }
+    }
+
+    pub fn wind_speed_label(&self) -> &'static str {
+        match self {
+            Unit::Metric => "km/h",
+            Unit::Imperial => "mph",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str_recognizes_imperial_case_insensitively() {
+        assert_eq!("imperial".parse::<Unit>(), Ok(Unit::Imperial));
+        assert_eq!("IMPERIAL".parse::<Unit>(), Ok(Unit::Imperial));
+    }
+
+    #[test]
+    fn from_str_defaults_to_metric() {
+        assert_eq!("metric".parse::<Unit>(), Ok(Unit::Metric));
+        assert_eq!("bogus".parse::<Unit>(), Ok(Unit::Metric));
+        assert_eq!("".parse::<Unit>(), Ok(Unit::Metric));
+    }
+}
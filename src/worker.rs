@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+
+use crate::{fetch_weather, hourly_len, units::Unit, LatLong, WeatherResponse};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+const CITY_DELAY: Duration = Duration::from_millis(500);
+
+/// How stale a cached forecast can be before `/weather` falls back to a live fetch.
+pub(crate) const CACHE_FRESHNESS: &str = "15 minutes";
+
+/// Runs forever, refreshing every known city's forecast on a fixed interval.
+pub async fn run(pool: PgPool) {
+    loop {
+        refresh_all_cities(&pool).await;
+        tokio::time::sleep(REFRESH_INTERVAL).await;
+    }
+}
+
+async fn refresh_all_cities(pool: &PgPool) {
+    let cities = match sqlx::query_as::<_, (String, f64, f64)>("SELECT name, lat, long FROM cities")
+        .fetch_all(pool)
+        .await
+    {
+        Ok(cities) => cities,
+        Err(err) => {
+            eprintln!("failed to load cities for refresh: {err}");
+            return;
+        }
+    };
+
+    for (name, lat, long) in cities {
+        let lat_long = LatLong {
+            latitude: lat,
+            longitude: long,
+        };
+
+        match fetch_weather(lat_long, Unit::Metric).await {
+            Ok(weather) => {
+                if let Err(err) = store_forecast(pool, &name, &weather).await {
+                    eprintln!("failed to store forecast for {name}: {err}");
+                }
+            }
+            Err(err) => {
+                eprintln!("failed to fetch weather for {name}: {err}");
+            }
+        }
+
+        tokio::time::sleep(CITY_DELAY).await;
+    }
+}
+
+async fn store_forecast(pool: &PgPool, city: &str, weather: &WeatherResponse) -> anyhow::Result<()> {
+    let hourly = &weather.hourly;
+
+    for i in 0..hourly_len(hourly) {
+        sqlx::query(
+            "INSERT INTO forecasts
+                (city, time, temperature, apparent_temperature, humidity, precipitation, wind_speed, wind_direction, pressure, fetched_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, now())
+             ON CONFLICT (city, time) DO UPDATE SET
+                temperature = EXCLUDED.temperature,
+                apparent_temperature = EXCLUDED.apparent_temperature,
+                humidity = EXCLUDED.humidity,
+                precipitation = EXCLUDED.precipitation,
+                wind_speed = EXCLUDED.wind_speed,
+                wind_direction = EXCLUDED.wind_direction,
+                pressure = EXCLUDED.pressure,
+                fetched_at = EXCLUDED.fetched_at",
+        )
+        .bind(city)
+        .bind(&hourly.time[i])
+        .bind(hourly.temperature_2m[i])
+        .bind(hourly.apparent_temperature[i])
+        .bind(hourly.relative_humidity_2m[i])
+        .bind(hourly.precipitation[i])
+        .bind(hourly.wind_speed_10m[i])
+        .bind(hourly.wind_direction_10m[i])
+        .bind(hourly.pressure_msl[i])
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
+}